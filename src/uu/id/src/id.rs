@@ -16,7 +16,7 @@
 // Option '--zero' does not exist for BSD's `id`, therefor '--zero' is only allowed together
 // with other options that are available on GNU's `id`.
 
-// spell-checker:ignore (ToDO) asid auditid auditinfo auid cstr egid emod euid getaudit getlogin gflag nflag pline rflag termid uflag gsflag zflag
+// spell-checker:ignore (ToDO) asid auditid auditinfo auid cstr egid emod euid getaudit getlogin gflag nflag pline rflag termid uflag gsflag zflag cflag pwflag prflag gecos jsonflag
 
 #![allow(non_camel_case_types)]
 #![allow(dead_code)]
@@ -26,7 +26,7 @@ extern crate uucore;
 
 use clap::{crate_version, App, Arg};
 use std::ffi::CStr;
-use uucore::entries::{self, Group, Locate, Passwd};
+use uucore::entries::{self, Locate, Passwd};
 pub use uucore::libc;
 use uucore::libc::{getlogin, uid_t};
 use uucore::process::{getegid, geteuid, getgid, getuid};
@@ -76,14 +76,277 @@ mod audit {
     }
 }
 
+// Fixture paths for `FileBackend`, overriding the real /etc/passwd and /etc/group.
+const ENV_PASSWD_FILE: &str = "UU_ID_PASSWD_FILE";
+const ENV_GROUP_FILE: &str = "UU_ID_GROUP_FILE";
+
+// Fixture overrides for `FileBackend`'s view of the calling process's ids,
+// so it doesn't have to fall back to raw libc calls to answer "who am I".
+const ENV_CURRENT_UID: &str = "UU_ID_CURRENT_UID";
+const ENV_CURRENT_GID: &str = "UU_ID_CURRENT_GID";
+const ENV_CURRENT_EUID: &str = "UU_ID_CURRENT_EUID";
+const ENV_CURRENT_EGID: &str = "UU_ID_CURRENT_EGID";
+
+fn env_uid(var: &str, fallback: fn() -> uid_t) -> uid_t {
+    std::env::var(var).ok().and_then(|v| v.parse().ok()).unwrap_or_else(fallback)
+}
+
+// A resolved passwd-database entry, backend-agnostic so callers never touch
+// `libc::passwd` or `uucore::entries::Passwd` directly.
+#[derive(Clone)]
+struct Identity {
+    name: String,
+    uid: uid_t,
+    gid: uid_t,
+    passwd: String,
+    info: String,
+    dir: String,
+    shell: String,
+    access_class: String,
+    passwd_change_time: i64,
+    expiration: i64,
+}
+
+// The four numeric IDs of the calling process, plus its login name.
+struct ProcessIds {
+    uid: uid_t,
+    gid: uid_t,
+    euid: uid_t,
+    egid: uid_t,
+    login: Option<String>,
+}
+
+// Resolves uids/names/gids and the calling process's own ids, either against
+// the host's libc NSS routines or a pure-Rust reader of /etc/passwd and
+// /etc/group (for fixture-driven tests and libc-NSS-less targets).
+trait Backend {
+    fn locate_user(&self, user: &str) -> Result<Identity, String>;
+    fn user_by_uid(&self, uid: uid_t) -> Result<Identity, String>;
+    fn group_name(&self, gid: uid_t) -> Option<String>;
+    fn supplementary_gids(&self, identity: &Identity) -> Vec<uid_t>;
+    fn process_groups(&self) -> Vec<uid_t>;
+    fn process_ids(&self) -> ProcessIds;
+}
+
+// The original backend: `uucore::entries` and the raw libc/`uucore::process` calls.
+struct LibcBackend;
+
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+fn bsd_fields(p: &Passwd) -> (String, i64, i64) {
+    (
+        p.user_access_class().to_string(),
+        p.passwd_change_time() as i64,
+        p.expiration() as i64,
+    )
+}
+
+#[cfg(not(any(target_vendor = "apple", target_os = "freebsd")))]
+fn bsd_fields(_p: &Passwd) -> (String, i64, i64) {
+    (String::new(), 0, 0)
+}
+
+impl From<Passwd> for Identity {
+    fn from(p: Passwd) -> Self {
+        let (access_class, passwd_change_time, expiration) = bsd_fields(&p);
+        Identity {
+            name: p.name().to_string(),
+            uid: p.uid(),
+            gid: p.gid(),
+            passwd: p.user_passwd().to_string(),
+            info: p.user_info().to_string(),
+            dir: p.user_dir().to_string(),
+            shell: p.user_shell().to_string(),
+            access_class,
+            passwd_change_time,
+            expiration,
+        }
+    }
+}
+
+impl Backend for LibcBackend {
+    fn locate_user(&self, user: &str) -> Result<Identity, String> {
+        Passwd::locate(user).map(Identity::from).map_err(|e| e.to_string())
+    }
+
+    fn user_by_uid(&self, uid: uid_t) -> Result<Identity, String> {
+        Passwd::locate(uid).map(Identity::from).map_err(|e| e.to_string())
+    }
+
+    fn group_name(&self, gid: uid_t) -> Option<String> {
+        entries::gid2grp(gid).ok()
+    }
+
+    fn supplementary_gids(&self, identity: &Identity) -> Vec<uid_t> {
+        Passwd::locate(identity.uid)
+            .map(|p| p.belongs_to())
+            .unwrap_or_else(|_| vec![identity.gid])
+    }
+
+    fn process_groups(&self) -> Vec<uid_t> {
+        entries::get_groups().unwrap_or_default()
+    }
+
+    fn process_ids(&self) -> ProcessIds {
+        let login = {
+            let c = unsafe { getlogin() };
+            if c.is_null() {
+                None
+            } else {
+                Some(cstr2cow!(c).into_owned())
+            }
+        };
+        ProcessIds {
+            uid: getuid(),
+            gid: getgid(),
+            euid: geteuid(),
+            egid: getegid(),
+            login,
+        }
+    }
+}
+
+// A pure-Rust reader of /etc/passwd and /etc/group (or the files named by
+// UU_ID_PASSWD_FILE/UU_ID_GROUP_FILE). The BSD-only access class/change-time/
+// expiration fields aren't in the portable 7-field passwd format, so they're
+// left blank.
+struct FileBackend {
+    passwd_path: String,
+    group_path: String,
+}
+
+impl FileBackend {
+    fn new() -> Self {
+        FileBackend {
+            passwd_path: std::env::var(ENV_PASSWD_FILE).unwrap_or_else(|_| "/etc/passwd".to_owned()),
+            group_path: std::env::var(ENV_GROUP_FILE).unwrap_or_else(|_| "/etc/group".to_owned()),
+        }
+    }
+
+    fn read_passwd(&self) -> Vec<Identity> {
+        std::fs::read_to_string(&self.passwd_path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?.to_owned();
+                let passwd = fields.next()?.to_owned();
+                let uid = fields.next()?.parse().ok()?;
+                let gid = fields.next()?.parse().ok()?;
+                let info = fields.next().unwrap_or_default().to_owned();
+                let dir = fields.next().unwrap_or_default().to_owned();
+                let shell = fields.next().unwrap_or_default().to_owned();
+                Some(Identity {
+                    name,
+                    uid,
+                    gid,
+                    passwd,
+                    info,
+                    dir,
+                    shell,
+                    access_class: String::new(),
+                    passwd_change_time: 0,
+                    expiration: 0,
+                })
+            })
+            .collect()
+    }
+
+    // (group name, gid, member names) for every line of the group file.
+    fn read_groups(&self) -> Vec<(String, uid_t, Vec<String>)> {
+        std::fs::read_to_string(&self.group_path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let name = fields.next()?.to_owned();
+                fields.next()?; // password
+                let gid = fields.next()?.parse().ok()?;
+                let members = fields
+                    .next()
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|m| !m.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+                Some((name, gid, members))
+            })
+            .collect()
+    }
+}
+
+impl Backend for FileBackend {
+    fn locate_user(&self, user: &str) -> Result<Identity, String> {
+        self.read_passwd()
+            .into_iter()
+            .find(|p| p.name == user)
+            .ok_or_else(|| format!("no such user: {}", user))
+    }
+
+    fn user_by_uid(&self, uid: uid_t) -> Result<Identity, String> {
+        self.read_passwd()
+            .into_iter()
+            .find(|p| p.uid == uid)
+            .ok_or_else(|| format!("no such uid: {}", uid))
+    }
+
+    fn group_name(&self, gid: uid_t) -> Option<String> {
+        self.read_groups()
+            .into_iter()
+            .find(|(_, g, _)| *g == gid)
+            .map(|(name, ..)| name)
+    }
+
+    fn supplementary_gids(&self, identity: &Identity) -> Vec<uid_t> {
+        let mut gids: Vec<uid_t> = self
+            .read_groups()
+            .into_iter()
+            .filter(|(_, _, members)| members.iter().any(|m| m == &identity.name))
+            .map(|(_, gid, _)| gid)
+            .collect();
+        if !gids.contains(&identity.gid) {
+            gids.insert(0, identity.gid);
+        }
+        gids
+    }
+
+    fn process_groups(&self) -> Vec<uid_t> {
+        let uid = env_uid(ENV_CURRENT_UID, getuid);
+        self.user_by_uid(uid)
+            .map(|identity| self.supplementary_gids(&identity))
+            .unwrap_or_else(|_| vec![env_uid(ENV_CURRENT_GID, getgid)])
+    }
+
+    fn process_ids(&self) -> ProcessIds {
+        let uid = env_uid(ENV_CURRENT_UID, getuid);
+        ProcessIds {
+            uid,
+            gid: env_uid(ENV_CURRENT_GID, getgid),
+            euid: env_uid(ENV_CURRENT_EUID, geteuid),
+            egid: env_uid(ENV_CURRENT_EGID, getegid),
+            login: self.user_by_uid(uid).ok().map(|p| p.name),
+        }
+    }
+}
+
+// LibcBackend, unless a fixture path was set via UU_ID_PASSWD_FILE/UU_ID_GROUP_FILE.
+fn default_backend() -> Box<dyn Backend> {
+    if std::env::var_os(ENV_PASSWD_FILE).is_some() || std::env::var_os(ENV_GROUP_FILE).is_some() {
+        Box::new(FileBackend::new())
+    } else {
+        Box::new(LibcBackend)
+    }
+}
+
 static ABOUT: &str = "The id utility displays the user and group names and numeric IDs, of the calling process, to the standard output. If the real and effective IDs are different, both are displayed, otherwise only the real ID is displayed.\n\nIf a user (login name or user ID) is specified, the user and group IDs of that user are displayed. In this case, the real and effective IDs are assumed to be the same.";
 
 mod options {
     pub const OPT_AUDIT: &str = "audit"; // GNU's id does not have this
+    pub const OPT_CONTEXT: &str = "context"; // BSD's id does not have this
     pub const OPT_EFFECTIVE_USER: &str = "user";
     pub const OPT_GROUP: &str = "group";
     pub const OPT_GROUPS: &str = "groups";
     pub const OPT_HUMAN_READABLE: &str = "human-readable"; // GNU's id does not have this
+    pub const OPT_FORMAT: &str = "output"; // GNU's id does not have this
     pub const OPT_NAME: &str = "name";
     pub const OPT_PASSWORD: &str = "password"; // GNU's id does not have this
     pub const OPT_REAL_ID: &str = "real";
@@ -96,6 +359,10 @@ fn get_usage() -> String {
 }
 
 pub fn uumain(args: impl uucore::Args) -> i32 {
+    uumain_with_backend(args, default_backend().as_ref())
+}
+
+fn uumain_with_backend(args: impl uucore::Args, backend: &dyn Backend) -> i32 {
     let usage = get_usage();
 
     let matches = App::new(executable!())
@@ -105,9 +372,25 @@ pub fn uumain(args: impl uucore::Args) -> i32 {
         .arg(
             Arg::with_name(options::OPT_AUDIT)
                 .short("A")
-                .conflicts_with_all(&[options::OPT_GROUP, options::OPT_EFFECTIVE_USER, options::OPT_HUMAN_READABLE, options::OPT_PASSWORD, options::OPT_GROUPS, options::OPT_ZERO])
+                .conflicts_with_all(&[options::OPT_GROUP, options::OPT_EFFECTIVE_USER, options::OPT_HUMAN_READABLE, options::OPT_PASSWORD, options::OPT_GROUPS, options::OPT_ZERO, options::OPT_CONTEXT, options::OPT_FORMAT])
                 .help("Display the process audit user ID and other process audit properties, which requires privilege (not available on Linux)."),
         )
+        .arg(
+            Arg::with_name(options::OPT_CONTEXT)
+                .short("Z")
+                .long(options::OPT_CONTEXT)
+                .conflicts_with_all(&[options::OPT_GROUP, options::OPT_EFFECTIVE_USER, options::OPT_GROUPS, options::OPT_HUMAN_READABLE, options::OPT_PASSWORD, options::OPT_AUDIT])
+                .help("Display only the security context of the process; with a USER operand, append it to the default output as context=... (not present on BSD)."),
+        )
+        .arg(
+            Arg::with_name(options::OPT_FORMAT)
+                .long(options::OPT_FORMAT)
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["json"])
+                .conflicts_with_all(&[options::OPT_GROUP, options::OPT_EFFECTIVE_USER, options::OPT_GROUPS, options::OPT_HUMAN_READABLE, options::OPT_PASSWORD, options::OPT_AUDIT])
+                .help("Emit the identity as structured JSON instead of the default text format (not present on BSD or GNU's id)."),
+        )
         .arg(
             Arg::with_name(options::OPT_EFFECTIVE_USER)
                 .short("u")
@@ -125,7 +408,7 @@ pub fn uumain(args: impl uucore::Args) -> i32 {
             Arg::with_name(options::OPT_GROUPS)
                 .short("G")
                 .long(options::OPT_GROUPS)
-                .conflicts_with_all(&[options::OPT_GROUP, options::OPT_EFFECTIVE_USER, options::OPT_HUMAN_READABLE, options::OPT_PASSWORD, options::OPT_AUDIT])
+                .conflicts_with_all(&[options::OPT_GROUP, options::OPT_EFFECTIVE_USER, options::OPT_HUMAN_READABLE, options::OPT_PASSWORD, options::OPT_AUDIT, options::OPT_CONTEXT, options::OPT_FORMAT])
                 .help("Display only the different group IDs as white-space separated numbers, in no particular order."),
         )
         .arg(
@@ -170,201 +453,305 @@ pub fn uumain(args: impl uucore::Args) -> i32 {
     let gsflag = matches.is_present(options::OPT_GROUPS);
     let rflag = matches.is_present(options::OPT_REAL_ID);
     let zflag = matches.is_present(options::OPT_ZERO);
+    let cflag = matches.is_present(options::OPT_CONTEXT);
+
+    let users: Vec<String> = matches
+        .values_of(options::ARG_USERS)
+        .map(|v| v.map(ToString::to_string).collect())
+        .unwrap_or_default();
+
+    let line_ending = if zflag { '\0' } else { '\n' };
+    let jsonflag = matches.value_of(options::OPT_FORMAT) == Some("json");
+
+    // `-Z` without `-ugG` and without a USER operand is the only thing
+    // printed; combined with a USER operand it instead decorates the
+    // default `id_print` line (see below). `--output=json` always goes
+    // through `id_print`'s JSON twin instead, so it is excluded here.
+    let context_only = cflag && !(uflag || gflag || gsflag) && !jsonflag && users.is_empty();
 
     // "default format" is when none of '-ugG' was used
     // could not implement these "required" rules with just clap
     if (nflag || rflag) && !(uflag || gflag || gsflag) {
         crash!(1, "cannot print only names or real IDs in default format");
     }
-    if (zflag) && !(uflag || gflag || gsflag) {
+    if zflag && !(uflag || gflag || gsflag) && !context_only {
         crash!(1, "option --zero not permitted in default format");
     }
 
-    let users: Vec<String> = matches
-        .values_of(options::ARG_USERS)
-        .map(|v| v.map(ToString::to_string).collect())
-        .unwrap_or_default();
-
     if matches.is_present(options::OPT_AUDIT) {
         auditid();
         return 0;
     }
 
-    let possible_pw = if users.is_empty() {
-        None
-    } else {
-        match Passwd::locate(users[0].as_str()) {
-            Ok(p) => Some(p),
-            Err(_) => crash!(1, "No such user/group: {}", users[0]),
+    if context_only {
+        match get_context() {
+            Ok(context) => print!("{}{}", context, line_ending),
+            Err(_) => crash!(1, "can't get process context"),
         }
+        return 0;
+    }
+
+    let opts = Options {
+        nflag,
+        uflag,
+        gflag,
+        gsflag,
+        rflag,
+        cflag,
+        jsonflag,
+        pwflag: matches.is_present(options::OPT_PASSWORD),
+        prflag: matches.is_present(options::OPT_HUMAN_READABLE),
+        line_ending,
     };
 
-    let line_ending = if zflag { '\0' } else { '\n' };
+    if users.is_empty() {
+        display_id(backend, None, &opts);
+        return 0;
+    }
+
+    let mut exit_code = 0;
+    for user in &users {
+        match backend.locate_user(user.as_str()) {
+            Ok(identity) => display_id(backend, Some(identity), &opts),
+            Err(_) => {
+                show_error!("No such user/group: {}", user);
+                exit_code = 1;
+            }
+        }
+    }
+    exit_code
+}
 
-    if gflag {
-        let id = possible_pw
-            .map(|p| p.gid())
-            .unwrap_or(if rflag { getgid() } else { getegid() });
+// Flags shared by every USER operand, controlling display_id's output.
+struct Options {
+    nflag: bool,
+    uflag: bool,
+    gflag: bool,
+    gsflag: bool,
+    rflag: bool,
+    cflag: bool,
+    jsonflag: bool,
+    pwflag: bool,
+    prflag: bool,
+    line_ending: char,
+}
+
+// Prints one block for a resolved user, or the calling process if None.
+fn display_id(backend: &dyn Backend, identity: Option<Identity>, opts: &Options) {
+    if opts.jsonflag {
+        id_print_json(backend, identity, opts.cflag);
+        return;
+    }
+
+    if opts.gflag {
+        let process_ids = backend.process_ids();
+        let id = identity
+            .map(|p| p.gid)
+            .unwrap_or(if opts.rflag { process_ids.gid } else { process_ids.egid });
         print!(
             "{}{}",
-            if nflag {
-                entries::gid2grp(id).unwrap_or_else(|_| id.to_string())
+            if opts.nflag {
+                backend.group_name(id).unwrap_or_else(|| id.to_string())
             } else {
                 id.to_string()
             },
-            line_ending
+            opts.line_ending
         );
-        return 0;
+        return;
     }
 
-    if uflag {
-        let id = possible_pw
-            .map(|p| p.uid())
-            .unwrap_or(if rflag { getuid() } else { geteuid() });
+    if opts.uflag {
+        let process_ids = backend.process_ids();
+        let id = identity
+            .map(|p| p.uid)
+            .unwrap_or(if opts.rflag { process_ids.uid } else { process_ids.euid });
         print!(
             "{}{}",
-            if nflag {
-                entries::uid2usr(id).unwrap_or_else(|_| id.to_string())
+            if opts.nflag {
+                backend
+                    .user_by_uid(id)
+                    .map(|p| p.name)
+                    .unwrap_or_else(|_| id.to_string())
             } else {
                 id.to_string()
             },
-            line_ending
+            opts.line_ending
         );
-        return 0;
+        return;
     }
 
-    if gsflag {
-        let delimiter = if zflag { "" } else { " " };
+    if opts.gsflag {
+        let delimiter = if opts.line_ending == '\0' { "" } else { " " };
         print!(
             "{}{}",
-            if nflag {
-                possible_pw
-                    .map(|p| p.belongs_to())
-                    .unwrap_or_else(|| entries::get_groups().unwrap())
-                    .iter()
-                    .map(|&id| entries::gid2grp(id).unwrap())
-                    .collect::<Vec<_>>()
-                    .join(delimiter)
-            } else {
-                possible_pw
-                    .map(|p| p.belongs_to())
-                    .unwrap_or_else(|| entries::get_groups().unwrap())
-                    .iter()
-                    .map(|&id| id.to_string())
-                    .collect::<Vec<_>>()
-                    .join(delimiter)
-            },
-            line_ending
+            group_list(backend, identity.as_ref(), opts.nflag).join(delimiter),
+            opts.line_ending
         );
-        return 0;
+        return;
     }
 
-    if matches.is_present(options::OPT_PASSWORD) {
-        pline(possible_pw.map(|v| v.uid()));
-        return 0;
-    };
+    if opts.pwflag {
+        pline(backend, identity.map(|v| v.uid));
+        return;
+    }
 
-    if matches.is_present(options::OPT_HUMAN_READABLE) {
-        pretty(possible_pw);
-        return 0;
+    if opts.prflag {
+        pretty(backend, identity);
+        return;
     }
 
-    if possible_pw.is_some() {
-        id_print(possible_pw, false, false)
+    if identity.is_some() {
+        id_print(backend, identity, false, false, opts.cflag)
     } else {
-        id_print(possible_pw, true, true)
+        id_print(backend, identity, true, true, opts.cflag)
     }
+}
 
-    0
+// Supplementary gids for identity, or the calling process if None; an
+// unmapped gid falls back to its numeric form rather than panicking.
+fn group_list(backend: &dyn Backend, identity: Option<&Identity>, as_names: bool) -> Vec<String> {
+    identity
+        .map(|p| backend.supplementary_gids(p))
+        .unwrap_or_else(|| backend.process_groups())
+        .iter()
+        .map(|&id| {
+            if as_names {
+                backend.group_name(id).unwrap_or_else(|| id.to_string())
+            } else {
+                id.to_string()
+            }
+        })
+        .collect()
 }
 
-fn pretty(possible_pw: Option<Passwd>) {
-    if let Some(p) = possible_pw {
-        print!("uid\t{}\ngroups\t", p.name());
-        println!(
-            "{}",
-            p.belongs_to()
-                .iter()
-                .map(|&gr| entries::gid2grp(gr).unwrap())
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
+// Multicall entry point for `groups`, the SuS3-mandated alias for `id -Gn`.
+pub fn uumain_groups(args: impl uucore::Args) -> i32 {
+    uumain_groups_with_backend(args, default_backend().as_ref())
+}
+
+fn uumain_groups_with_backend(args: impl uucore::Args, backend: &dyn Backend) -> i32 {
+    let usage = format!("{0} [OPTION]... [USERNAME]...", executable!());
+
+    let matches = App::new(executable!())
+        .version(crate_version!())
+        .about("Print the groups a user is in to standard output. If no username is specified, use the current process.")
+        .usage(&usage[..])
+        .arg(
+            Arg::with_name(options::ARG_USERS)
+                .multiple(true)
+                .takes_value(true)
+                .value_name(options::ARG_USERS),
+        )
+        .get_matches_from(args);
+
+    let users: Vec<String> = matches
+        .values_of(options::ARG_USERS)
+        .map(|v| v.map(ToString::to_string).collect())
+        .unwrap_or_default();
+
+    if users.is_empty() {
+        println!("{}", group_list(backend, None, true).join(" "));
+        return 0;
+    }
+
+    let mut exit_code = 0;
+    for user in &users {
+        match backend.locate_user(user.as_str()) {
+            Ok(identity) => println!("{} : {}", user, group_list(backend, Some(&identity), true).join(" ")),
+            Err(_) => {
+                show_error!("No such user/group: {}", user);
+                exit_code = 1;
+            }
+        }
+    }
+    exit_code
+}
+
+// Reads the calling process's security context (SELinux/SMACK).
+fn get_context() -> std::io::Result<String> {
+    let context = std::fs::read_to_string("/proc/self/attr/current")?;
+    let context = context.trim_end_matches(|c| c == '\0' || c == '\n');
+    if context.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "no security context available",
+        ));
+    }
+    Ok(context.to_owned())
+}
+
+fn pretty(backend: &dyn Backend, identity: Option<Identity>) {
+    if let Some(p) = identity {
+        print!("uid\t{}\ngroups\t", p.name);
+        println!("{}", group_list(backend, Some(&p), true).join(" "));
     } else {
-        let login = cstr2cow!(getlogin() as *const _);
-        let rid = getuid();
-        if let Ok(p) = Passwd::locate(rid) {
-            if login == p.name() {
-                println!("login\t{}", login);
+        let process_ids = backend.process_ids();
+        let rid = process_ids.uid;
+        if let Ok(p) = backend.user_by_uid(rid) {
+            if process_ids.login.as_deref() == Some(p.name.as_str()) {
+                println!("login\t{}", p.name);
             }
-            println!("uid\t{}", p.name());
+            println!("uid\t{}", p.name);
         } else {
             println!("uid\t{}", rid);
         }
 
-        let eid = getegid();
+        let eid = process_ids.egid;
         if eid == rid {
-            if let Ok(p) = Passwd::locate(eid) {
-                println!("euid\t{}", p.name());
+            if let Ok(p) = backend.user_by_uid(eid) {
+                println!("euid\t{}", p.name);
             } else {
                 println!("euid\t{}", eid);
             }
         }
 
-        let rid = getgid();
+        let rid = process_ids.gid;
         if rid != eid {
-            if let Ok(g) = Group::locate(rid) {
-                println!("euid\t{}", g.name());
+            if let Some(name) = backend.group_name(rid) {
+                println!("euid\t{}", name);
             } else {
                 println!("euid\t{}", rid);
             }
         }
 
-        println!(
-            "groups\t{}",
-            entries::get_groups()
-                .unwrap()
-                .iter()
-                .map(|&gr| entries::gid2grp(gr).unwrap())
-                .collect::<Vec<_>>()
-                .join(" ")
-        );
+        println!("groups\t{}", group_list(backend, None, true).join(" "));
     }
 }
 
 #[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
-fn pline(possible_uid: Option<uid_t>) {
-    let uid = possible_uid.unwrap_or_else(getuid);
-    let pw = Passwd::locate(uid).unwrap();
+fn pline(backend: &dyn Backend, possible_uid: Option<uid_t>) {
+    let uid = possible_uid.unwrap_or_else(|| backend.process_ids().uid);
+    let pw = match backend.user_by_uid(uid) {
+        Ok(p) => p,
+        Err(e) => crash!(1, "Could not find uid {}: {}", uid, e),
+    };
 
     println!(
         "{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
-        pw.name(),
-        pw.user_passwd(),
-        pw.uid(),
-        pw.gid(),
-        pw.user_access_class(),
-        pw.passwd_change_time(),
-        pw.expiration(),
-        pw.user_info(),
-        pw.user_dir(),
-        pw.user_shell()
+        pw.name,
+        pw.passwd,
+        pw.uid,
+        pw.gid,
+        pw.access_class,
+        pw.passwd_change_time,
+        pw.expiration,
+        pw.info,
+        pw.dir,
+        pw.shell
     );
 }
 
 #[cfg(target_os = "linux")]
-fn pline(possible_uid: Option<uid_t>) {
-    let uid = possible_uid.unwrap_or_else(getuid);
-    let pw = Passwd::locate(uid).unwrap();
+fn pline(backend: &dyn Backend, possible_uid: Option<uid_t>) {
+    let uid = possible_uid.unwrap_or_else(|| backend.process_ids().uid);
+    let pw = match backend.user_by_uid(uid) {
+        Ok(p) => p,
+        Err(e) => crash!(1, "Could not find uid {}: {}", uid, e),
+    };
 
     println!(
         "{}:{}:{}:{}:{}:{}:{}",
-        pw.name(),
-        pw.user_passwd(),
-        pw.uid(),
-        pw.gid(),
-        pw.user_info(),
-        pw.user_dir(),
-        pw.user_shell()
+        pw.name, pw.passwd, pw.uid, pw.gid, pw.info, pw.dir, pw.shell
     );
 }
 
@@ -388,37 +775,219 @@ fn auditid() {
     println!("asid={}", auditinfo.ai_asid);
 }
 
-fn id_print(possible_pw: Option<Passwd>, p_euid: bool, p_egid: bool) {
-    let (uid, gid) = possible_pw
-        .map(|p| (p.uid(), p.gid()))
-        .unwrap_or((getuid(), getgid()));
+fn id_print(backend: &dyn Backend, identity: Option<Identity>, p_euid: bool, p_egid: bool, p_context: bool) {
+    let process_ids = backend.process_ids();
+    let (uid, gid) = identity
+        .as_ref()
+        .map(|p| (p.uid, p.gid))
+        .unwrap_or((process_ids.uid, process_ids.gid));
 
-    let groups = match Passwd::locate(uid) {
-        Ok(p) => p.belongs_to(),
+    let resolved = match backend.user_by_uid(uid) {
+        Ok(p) => p,
         Err(e) => crash!(1, "Could not find uid {}: {}", uid, e),
     };
+    let groups = backend.supplementary_gids(&resolved);
 
-    print!("uid={}({})", uid, entries::uid2usr(uid).unwrap());
-    print!(" gid={}({})", gid, entries::gid2grp(gid).unwrap());
+    print!("uid={}({})", uid, resolved.name);
+    print!(" gid={}({})", gid, backend.group_name(gid).unwrap_or_else(|| gid.to_string()));
 
-    let euid = geteuid();
+    let euid = process_ids.euid;
     if p_euid && (euid != uid) {
-        print!(" euid={}({})", euid, entries::uid2usr(euid).unwrap());
+        print!(
+            " euid={}({})",
+            euid,
+            backend.user_by_uid(euid).map(|p| p.name).unwrap_or_else(|_| euid.to_string())
+        );
     }
 
-    let egid = getegid();
+    let egid = process_ids.egid;
     if p_egid && (egid != gid) {
-        print!(" egid={}({})", euid, entries::gid2grp(egid).unwrap());
+        print!(
+            " egid={}({})",
+            egid,
+            backend.group_name(egid).unwrap_or_else(|| egid.to_string())
+        );
     }
 
-    println!(
+    print!(
         " groups={}",
         groups
             .iter()
-            .map(|&gr| format!("{}({})", gr, entries::gid2grp(gr).unwrap()))
+            .map(|&gr| format!("{}({})", gr, backend.group_name(gr).unwrap_or_else(|| gr.to_string())))
             .collect::<Vec<_>>()
             .join(",")
     );
+
+    if p_context {
+        match get_context() {
+            Ok(context) => print!(" context={}", context),
+            Err(_) => crash!(1, "can't get process context"),
+        }
+    }
+
+    println!();
 }
 
-fn get_groups() ->
+// `id_print`'s JSON twin: same resolution path (user_by_uid + supplementary_gids),
+// but an unmapped gid serializes as "name": null instead of falling back to the
+// numeric id as text. Builds the line as a String so it can be unit-tested
+// without capturing stdout.
+fn identity_json(backend: &dyn Backend, identity: Option<Identity>, p_context: bool) -> String {
+    let process_ids = backend.process_ids();
+    let (uid, gid) = identity
+        .as_ref()
+        .map(|p| (p.uid, p.gid))
+        .unwrap_or((process_ids.uid, process_ids.gid));
+
+    let resolved = match backend.user_by_uid(uid) {
+        Ok(p) => p,
+        Err(e) => crash!(1, "Could not find uid {}: {}", uid, e),
+    };
+    let groups = backend.supplementary_gids(&resolved);
+
+    // A named user's effective ids are taken to be its real ids (see ABOUT).
+    let (euid, egid) = if identity.is_some() {
+        (uid, gid)
+    } else {
+        (process_ids.euid, process_ids.egid)
+    };
+
+    let groups_json = groups
+        .iter()
+        .map(|&gr| {
+            let name = match backend.group_name(gr) {
+                Some(name) => format!("\"{}\"", json_escape(&name)),
+                None => "null".to_owned(),
+            };
+            format!("{{\"gid\":{},\"name\":{}}}", gr, name)
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let mut out = format!(
+        "{{\"uid\":{},\"gid\":{},\"euid\":{},\"egid\":{},\"name\":\"{}\",\"groups\":[{}]",
+        uid,
+        gid,
+        euid,
+        egid,
+        json_escape(&resolved.name),
+        groups_json
+    );
+
+    if p_context {
+        match get_context() {
+            Ok(context) => out.push_str(&format!(",\"context\":\"{}\"", json_escape(&context))),
+            Err(_) => crash!(1, "can't get process context"),
+        }
+    }
+
+    out.push('}');
+    out
+}
+
+fn id_print_json(backend: &dyn Backend, identity: Option<Identity>, p_context: bool) {
+    println!("{}", identity_json(backend, identity, p_context));
+}
+
+// Escapes `"` and `\` for embedding s in a JSON string literal.
+fn json_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static FIXTURE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    fn write_fixture(contents: &str) -> String {
+        let n = FIXTURE_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let mut path = std::env::temp_dir();
+        path.push(format!("uu_id_test_{}_{}", std::process::id(), n));
+        std::fs::File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn fixture_backend(passwd: &str, group: &str) -> FileBackend {
+        FileBackend {
+            passwd_path: write_fixture(passwd),
+            group_path: write_fixture(group),
+        }
+    }
+
+    #[test]
+    fn file_backend_locates_and_resolves_users() {
+        let backend = fixture_backend(
+            "alice:x:1000:1000:Alice:/home/alice:/bin/sh\nbob:x:1001:1001:Bob:/home/bob:/bin/sh\n",
+            "users:x:1000:alice,bob\nwheel:x:10:alice\n",
+        );
+
+        let alice = backend.locate_user("alice").unwrap();
+        assert_eq!(alice.uid, 1000);
+        assert_eq!(backend.user_by_uid(1001).unwrap().name, "bob");
+        assert!(backend.locate_user("nobody").is_err());
+
+        assert_eq!(backend.group_name(1000), Some("users".to_owned()));
+        assert_eq!(backend.group_name(9999), None);
+
+        let mut gids = backend.supplementary_gids(&alice);
+        gids.sort_unstable();
+        assert_eq!(gids, vec![10, 1000]);
+    }
+
+    #[test]
+    fn file_backend_process_ids_honor_env_overrides() {
+        let backend = fixture_backend("root:x:0:0:root:/root:/bin/sh\n", "root:x:0:\n");
+
+        std::env::set_var(ENV_CURRENT_UID, "0");
+        std::env::set_var(ENV_CURRENT_GID, "0");
+        std::env::set_var(ENV_CURRENT_EUID, "0");
+        std::env::set_var(ENV_CURRENT_EGID, "0");
+
+        let ids = backend.process_ids();
+        assert_eq!((ids.uid, ids.gid, ids.euid, ids.egid), (0, 0, 0, 0));
+        assert_eq!(ids.login.as_deref(), Some("root"));
+        assert_eq!(backend.process_groups(), vec![0]);
+
+        std::env::remove_var(ENV_CURRENT_UID);
+        std::env::remove_var(ENV_CURRENT_GID);
+        std::env::remove_var(ENV_CURRENT_EUID);
+        std::env::remove_var(ENV_CURRENT_EGID);
+    }
+
+    #[test]
+    fn identity_json_reports_null_name_for_unmapped_gid() {
+        let backend = fixture_backend("alice:x:1000:2000:Alice:/home/alice:/bin/sh\n", "");
+        let identity = backend.locate_user("alice").unwrap();
+
+        let json = identity_json(&backend, Some(identity), false);
+
+        assert!(json.starts_with("{\"uid\":1000,\"gid\":2000,"));
+        assert!(json.contains("\"groups\":[{\"gid\":2000,\"name\":null}]"));
+        assert!(json.ends_with('}'));
+    }
+
+    #[test]
+    fn uumain_processes_every_user_operand_despite_a_bad_one() {
+        let backend = fixture_backend(
+            "alice:x:1000:1000:Alice:/home/alice:/bin/sh\nbob:x:1001:1001:Bob:/home/bob:/bin/sh\n",
+            "",
+        );
+        let args = vec!["id", "alice", "nobody", "bob"].into_iter().map(String::from);
+
+        assert_eq!(uumain_with_backend(args, &backend), 1);
+    }
+
+    #[test]
+    fn uumain_groups_processes_every_username_operand_despite_a_bad_one() {
+        let backend = fixture_backend(
+            "alice:x:1000:1000:Alice:/home/alice:/bin/sh\nbob:x:1001:1001:Bob:/home/bob:/bin/sh\n",
+            "wheel:x:10:alice,bob\n",
+        );
+        let args = vec!["groups", "alice", "nobody", "bob"].into_iter().map(String::from);
+
+        assert_eq!(uumain_groups_with_backend(args, &backend), 1);
+    }
+}